@@ -26,22 +26,74 @@ use libc;
 use conversion::ToPyObject;
 use std::ffi::CString;
 
+/// Internal representation of a `PyErr`.
+///
+/// Keeping this as an enum lets `PyErr` defer building the exception value
+/// until it is actually needed: the common case of "format an error message,
+/// but only if the error is ever observed" should not pay for a `PyObject`
+/// (or the string formatting behind it) on the hot success path.
+enum PyErrState<'p> {
+    /// The exception type is known, but the value hasn't been built yet.
+    /// `matches()` can compare against `ptype` without running `value`.
+    Lazy {
+        ptype: PyType<'p>,
+        value: Box<dyn FnOnce(Python<'p>) -> PyObject<'p> + 'p>
+    },
+    /// The raw `(ptype, pvalue, ptraceback)` tuple as handed back by the
+    /// Python C API; `pvalue` may not yet be an instance of `ptype`.
+    FfiTuple {
+        ptype: PyObject<'p>,
+        pvalue: Option<PyObject<'p>>,
+        ptraceback: Option<PyObject<'p>>
+    },
+    /// The result of `PyErr_NormalizeException`: `pvalue` is guaranteed to
+    /// be an instance of `ptype`.
+    Normalized {
+        ptype: PyObject<'p>,
+        pvalue: PyObject<'p>,
+        ptraceback: Option<PyObject<'p>>
+    },
+    /// Python 3.12+: the single, already-normalized exception object as
+    /// returned by `PyErr_GetRaisedException`. The interpreter itself only
+    /// tracks this one object, so `ptype`/`ptraceback` are derived on demand
+    /// (via `pvalue.get_type()` / `PyException_GetTraceback`) instead of
+    /// being stored redundantly.
+    #[cfg(Py_3_12)]
+    Raised(PyObject<'p>)
+}
+
 /// Represents a Python exception that was raised.
-#[derive(Clone, Debug)]
+///
+/// Note: earlier versions of this crate derived `Clone` for `PyErr`. Now that the
+/// `Lazy` state can hold a boxed `FnOnce` value factory, which cannot be cloned,
+/// `PyErr` no longer implements `Clone`. This is a breaking change for any
+/// downstream code relying on cloning a `PyErr`; callers that need to keep a copy
+/// around should `normalize()` the error first and clone the resulting `ptype`/
+/// instance `PyObject`s themselves.
+///
+/// This is also a breaking change in a second way: the public `ptype`/`pvalue`/
+/// `ptraceback` fields are gone now that the error's internal representation is an
+/// enum, not a tuple. `get_type()` and `instance()` replace `ptype`/`pvalue`;
+/// traceback access is replaced by the `ptraceback()` method below.
 pub struct PyErr<'p> {
-    /// The type of the exception. This should be either a `PyClass` or a `PyType`.
-    pub ptype : PyObject<'p>,
-    /// The value of the exception.
-    /// 
-    /// This can be either an instance of `ptype`,
-    /// a tuple of arguments to be passed to `ptype`'s constructor,
-    /// or a single argument to be passed to `ptype`'s constructor.
-    /// Call `PyErr::instance()` to get the exception instance in all cases.
-    pub pvalue : Option<PyObject<'p>>,
-    /// The `PyTraceBack` object associated with the error.
-    pub ptraceback : Option<PyObject<'p>>
+    state: PyErrState<'p>
 }
 
+impl<'p> std::fmt::Debug for PyErr<'p> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self.state {
+            PyErrState::Lazy { ref ptype, .. } =>
+                write!(f, "PyErr {{ ptype: {:?}, pvalue: <lazy> }}", ptype.as_object()),
+            PyErrState::FfiTuple { ref ptype, ref pvalue, .. } =>
+                write!(f, "PyErr {{ ptype: {:?}, pvalue: {:?} }}", ptype, pvalue),
+            PyErrState::Normalized { ref ptype, ref pvalue, .. } =>
+                write!(f, "PyErr {{ ptype: {:?}, pvalue: {:?} }}", ptype, pvalue),
+            #[cfg(Py_3_12)]
+            PyErrState::Raised(ref pvalue) =>
+                write!(f, "PyErr {{ pvalue: {:?} }}", pvalue)
+        }
+    }
+}
 
 /// Represents the result of a Python call.
 pub type PyResult<'p, T> = Result<T, PyErr<'p>>;
@@ -57,26 +109,61 @@ impl <'p> PyErr<'p> {
     /// The error is cleared from the Python interpreter.
     /// If no error is set, returns a `SystemError`.
     pub fn fetch(py : Python<'p>) -> PyErr<'p> {
+        match PyErr::take(py) {
+            Some(err) => err,
+            None => PyErr::new_lazy_init(py.get_type::<exc::SystemError>(), None)
+        }
+    }
+
+    /// Retrieves the current error from the Python interpreter's global state, if one is set.
+    /// The error is cleared from the Python interpreter.
+    /// Returns `None` if no error is set, instead of fabricating a `SystemError`
+    /// like `fetch` does; useful for probing for an error without committing to handling one.
+    ///
+    /// On Python 3.12+ this uses `PyErr_GetRaisedException`, which hands back a single
+    /// already-normalized exception object instead of the deprecated `(type, value,
+    /// traceback)` tri-tuple, avoiding both the deprecation warning and an extra
+    /// normalization round-trip.
+    #[cfg(not(Py_3_12))]
+    pub fn take(py : Python<'p>) -> Option<PyErr<'p>> {
+        if !PyErr::occurred(py) {
+            return None;
+        }
         unsafe {
             let mut ptype      : *mut ffi::PyObject = std::mem::uninitialized();
             let mut pvalue     : *mut ffi::PyObject = std::mem::uninitialized();
             let mut ptraceback : *mut ffi::PyObject = std::mem::uninitialized();
             ffi::PyErr_Fetch(&mut ptype, &mut pvalue, &mut ptraceback);
-            PyErr::new_from_ffi_tuple(py, ptype, pvalue, ptraceback)
+            Some(PyErr::new_from_ffi_tuple(py, ptype, pvalue, ptraceback))
         }
     }
 
+    /// See the `not(Py_3_12)` overload above.
+    #[cfg(Py_3_12)]
+    pub fn take(py : Python<'p>) -> Option<PyErr<'p>> {
+        if !PyErr::occurred(py) {
+            return None;
+        }
+        unsafe {
+            let raised = ffi::PyErr_GetRaisedException();
+            Some(PyErr { state: PyErrState::Raised(PyObject::from_owned_ptr(py, raised)) })
+        }
+    }
+
+    #[cfg(not(Py_3_12))]
     unsafe fn new_from_ffi_tuple(py: Python<'p>, ptype: *mut ffi::PyObject, pvalue: *mut ffi::PyObject, ptraceback: *mut ffi::PyObject) -> PyErr<'p> {
         // Note: must not panic to ensure all owned pointers get acquired correctly,
         // and because we mustn't panic in normalize().
         PyErr {
-            ptype: if ptype.is_null() {
-                        py.get_type::<exc::SystemError>().into_object()
-                   } else {
-                        PyObject::from_owned_ptr(py, ptype)
-                   },
-            pvalue: PyObject::from_owned_ptr_opt(py, pvalue),
-            ptraceback: PyObject::from_owned_ptr_opt(py, ptraceback)
+            state: PyErrState::FfiTuple {
+                ptype: if ptype.is_null() {
+                            py.get_type::<exc::SystemError>().into_object()
+                       } else {
+                            PyObject::from_owned_ptr(py, ptype)
+                       },
+                pvalue: PyObject::from_owned_ptr_opt(py, pvalue),
+                ptraceback: PyObject::from_owned_ptr_opt(py, ptraceback)
+            }
         }
     }
 
@@ -97,9 +184,11 @@ impl <'p> PyErr<'p> {
     fn new_helper(ty: PyType<'p>, value: PyObject<'p>) -> PyErr<'p> {
         assert!(unsafe { ffi::PyExceptionClass_Check(ty.as_object().as_ptr()) } != 0);
         PyErr {
-            ptype: ty.into_object(),
-            pvalue: Some(value),
-            ptraceback: None
+            state: PyErrState::FfiTuple {
+                ptype: ty.into_object(),
+                pvalue: Some(value),
+                ptraceback: None
+            }
         }
     }
 
@@ -116,21 +205,27 @@ impl <'p> PyErr<'p> {
         let py = obj.python();
         if unsafe { ffi::PyExceptionInstance_Check(obj.as_ptr()) } != 0 {
             PyErr {
-                ptype: unsafe { PyObject::from_borrowed_ptr(py, ffi::PyExceptionInstance_Class(obj.as_ptr())) },
-                pvalue: Some(obj),
-                ptraceback: None
+                state: PyErrState::FfiTuple {
+                    ptype: unsafe { PyObject::from_borrowed_ptr(py, ffi::PyExceptionInstance_Class(obj.as_ptr())) },
+                    pvalue: Some(obj),
+                    ptraceback: None
+                }
             }
         } else if unsafe { ffi::PyExceptionClass_Check(obj.as_ptr()) } != 0 {
             PyErr {
-                ptype: obj,
-                pvalue: None,
-                ptraceback: None
+                state: PyErrState::FfiTuple {
+                    ptype: obj,
+                    pvalue: None,
+                    ptraceback: None
+                }
             }
         } else {
             PyErr {
-                ptype: py.get_type::<exc::TypeError>().into_object(),
-                pvalue: Some("exceptions must derive from BaseException".to_py_object(py).into_object()),
-                ptraceback: None
+                state: PyErrState::FfiTuple {
+                    ptype: py.get_type::<exc::TypeError>().into_object(),
+                    pvalue: Some("exceptions must derive from BaseException".to_py_object(py).into_object()),
+                    ptraceback: None
+                }
             }
         }
     }
@@ -141,9 +236,30 @@ impl <'p> PyErr<'p> {
     #[inline]
     pub fn new_lazy_init(exc: PyType<'p>, value: Option<PyObject<'p>>) -> PyErr<'p> {
         PyErr {
-            ptype: exc.into_object(),
-            pvalue: value,
-            ptraceback: None
+            state: PyErrState::FfiTuple {
+                ptype: exc.into_object(),
+                pvalue: value,
+                ptraceback: None
+            }
+        }
+    }
+
+    /// Construct a new error whose *value* is built lazily, only once the error is
+    /// actually observed (via `restore`, `instance`, or `normalize`).
+    ///
+    /// As with `new_lazy_init`, the exception type is given up front, so `matches()`
+    /// can short-circuit on it without ever running `value_factory`; unlike
+    /// `new_lazy_init`, no value object has to be built by the caller. This is
+    /// useful for errors whose message requires formatting that would be wasted
+    /// work if the error ends up being ignored or merely probed for its type.
+    pub fn new_lazy<F>(exc_type: PyType<'p>, value_factory: F) -> PyErr<'p>
+        where F: FnOnce(Python<'p>) -> PyObject<'p> + 'p
+    {
+        PyErr {
+            state: PyErrState::Lazy {
+                ptype: exc_type,
+                value: Box::new(value_factory)
+            }
         }
     }
 
@@ -162,9 +278,54 @@ impl <'p> PyErr<'p> {
     /// Return true if the current exception matches the exception in `exc`.
     /// If `exc` is a class object, this also returns `true` when `self` is an instance of a subclass.
     /// If `exc` is a tuple, all exceptions in the tuple (and recursively in subtuples) are searched for a match.
+    ///
+    /// This only ever inspects the stored exception type, so it never forces
+    /// a lazily-constructed value to be built.
     #[inline]
     pub fn matches(&self, exc: &PyObject) -> bool {
-        unsafe { ffi::PyErr_GivenExceptionMatches(self.ptype.as_ptr(), exc.as_ptr()) != 0 }
+        unsafe { ffi::PyErr_GivenExceptionMatches(self.ptype_object().as_ptr(), exc.as_ptr()) != 0 }
+    }
+
+    /// Returns true if this error's type is `T` (or a subclass of it), checked against
+    /// the compile-time-known class object rather than one built by hand, e.g.
+    /// `if err.is_instance_of::<exc::KeyError>(py) { ... }`.
+    #[inline]
+    pub fn is_instance_of<T>(&self, py: Python<'p>) -> bool
+        where T: PythonObjectWithTypeObject<'p>
+    {
+        self.matches(&py.get_type::<T>().into_object())
+    }
+
+    /// Normalizes the error and returns true if its actual instance is an instance of `exc`.
+    ///
+    /// Unlike `matches`, which only inspects the stored exception type, this tests the
+    /// normalized instance itself via `PyObject_IsInstance`.
+    pub fn is_instance(&mut self, exc: &PyObject) -> bool {
+        let instance = self.instance();
+        match unsafe { ffi::PyObject_IsInstance(instance.as_ptr(), exc.as_ptr()) } {
+            1 => true,
+            0 => false,
+            _ => {
+                // -1 means `exc` wasn't a class (or tuple of classes), and
+                // PyObject_IsInstance has already set a Python exception explaining
+                // why. Clear it rather than leaving it set for some unrelated later
+                // `PyErr::occurred()`/`fetch()` to pick up, and report "not an
+                // instance" instead of propagating a surprise error.
+                unsafe { ffi::PyErr_Clear(); }
+                false
+            }
+        }
+    }
+
+    /// Returns the stored exception type as a `PyObject`, without materializing a lazy value.
+    fn ptype_object(&self) -> PyObject<'p> {
+        match self.state {
+            PyErrState::Lazy { ref ptype, .. } => ptype.as_object().clone(),
+            PyErrState::FfiTuple { ref ptype, .. } => ptype.clone(),
+            PyErrState::Normalized { ref ptype, .. } => ptype.clone(),
+            #[cfg(Py_3_12)]
+            PyErrState::Raised(ref pvalue) => pvalue.get_type().into_object()
+        }
     }
 
     /// Normalizes the error. This ensures that the exception value is an instance of the exception type.
@@ -176,18 +337,55 @@ impl <'p> PyErr<'p> {
         }
         // This is safe as long as normalized() doesn't unwind due to a panic.
     }
-    
+
     /// Helper function for normalizing the error by deconstructing and reconstructing the PyErr.
     /// Must not panic for safety in normalize()
     fn into_normalized(self) -> PyErr<'p> {
-        let PyErr { ptype, pvalue, ptraceback } = self;
+        if let PyErrState::Normalized { .. } = self.state {
+            return self;
+        }
+        #[cfg(Py_3_12)]
+        {
+            if let PyErrState::Raised(_) = self.state {
+                return self;
+            }
+        }
+        let (ptype, pvalue, ptraceback) = self.into_ffi_tuple();
         let py = ptype.python();
         let mut ptype = ptype.steal_ptr();
         let mut pvalue = pvalue.steal_ptr();
         let mut ptraceback = ptraceback.steal_ptr();
         unsafe {
             ffi::PyErr_NormalizeException(&mut ptype, &mut pvalue, &mut ptraceback);
-            PyErr::new_from_ffi_tuple(py, ptype, pvalue, ptraceback)
+            PyErr {
+                state: PyErrState::Normalized {
+                    ptype: PyObject::from_owned_ptr(py, ptype),
+                    pvalue: PyObject::from_owned_ptr(py, pvalue),
+                    ptraceback: PyObject::from_owned_ptr_opt(py, ptraceback)
+                }
+            }
+        }
+    }
+
+    /// Deconstructs this error into a `(ptype, pvalue, ptraceback)` tuple, building
+    /// the value from its factory closure if it hasn't been built yet.
+    fn into_ffi_tuple(self) -> (PyObject<'p>, Option<PyObject<'p>>, Option<PyObject<'p>>) {
+        match self.state {
+            PyErrState::Lazy { ptype, value } => {
+                let py = ptype.python();
+                let pvalue = value(py);
+                (ptype.into_object(), Some(pvalue), None)
+            }
+            PyErrState::FfiTuple { ptype, pvalue, ptraceback } => (ptype, pvalue, ptraceback),
+            PyErrState::Normalized { ptype, pvalue, ptraceback } => (ptype, Some(pvalue), ptraceback),
+            #[cfg(Py_3_12)]
+            PyErrState::Raised(pvalue) => {
+                let ptype = pvalue.get_type().into_object();
+                let ptraceback = unsafe {
+                    PyObject::from_owned_ptr_opt(pvalue.python(), ffi::PyException_GetTraceback(pvalue.as_ptr()))
+                };
+                (ptype, Some(pvalue), ptraceback)
+            }
         }
     }
 
@@ -196,11 +394,12 @@ impl <'p> PyErr<'p> {
     /// If the exception type is an old-style class, returns `oldstyle::PyClass`.
     #[cfg(feature="python27-sys")]
     pub fn get_type(&self) -> PyType<'p> {
-        let py = self.ptype.python();
-        match self.ptype.clone().cast_into::<PyType>() {
+        let ptype = self.ptype_object();
+        let py = ptype.python();
+        match ptype.clone().cast_into::<PyType>() {
             Ok(t)  => t,
             Err(_) =>
-                match self.ptype.cast_as::<PyClass>() {
+                match ptype.cast_as::<PyClass>() {
                     Ok(_)  => py.get_type::<PyClass>(),
                     Err(_) => py.None().get_type().clone()
                 }
@@ -210,8 +409,9 @@ impl <'p> PyErr<'p> {
     /// Retrieves the exception type.
     #[cfg(not(feature="python27-sys"))]
     pub fn get_type(&self) -> PyType<'p> {
-        let py = self.ptype.python();
-        match self.ptype.clone().cast_into::<PyType>() {
+        let ptype = self.ptype_object();
+        let py = ptype.python();
+        match ptype.clone().cast_into::<PyType>() {
             Ok(t)  => t,
             Err(_) => py.None().get_type().clone()
         }
@@ -219,25 +419,65 @@ impl <'p> PyErr<'p> {
 
     /// Retrieves the exception instance for this error.
     /// This method takes `&mut self` because the error might need
-    /// to be normalized in order to create the exception instance.
+    /// to be normalized (and a lazy value built) in order to produce the instance.
     pub fn instance(&mut self) -> PyObject<'p> {
         self.normalize();
-        match self.pvalue {
-            Some(ref instance) => instance.clone(),
-            None => self.ptype.python().None()
+        match self.state {
+            PyErrState::Normalized { ref pvalue, .. } => pvalue.clone(),
+            #[cfg(Py_3_12)]
+            PyErrState::Raised(ref pvalue) => pvalue.clone(),
+            _ => unreachable!("normalize() always leaves self in a normalized state")
+        }
+    }
+
+    /// Retrieves the traceback associated with this error, if any.
+    /// This method takes `&mut self` because the error might need
+    /// to be normalized (and a lazy value built) in order to produce the traceback.
+    pub fn ptraceback(&mut self) -> Option<PyObject<'p>> {
+        self.normalize();
+        match self.state {
+            PyErrState::Normalized { ref ptraceback, .. } => ptraceback.clone(),
+            #[cfg(Py_3_12)]
+            PyErrState::Raised(ref pvalue) => unsafe {
+                PyObject::from_owned_ptr_opt(pvalue.python(), ffi::PyException_GetTraceback(pvalue.as_ptr()))
+            },
+            _ => unreachable!("normalize() always leaves self in a normalized state")
         }
     }
 
     /// Writes the error back to the Python interpreter's global state.
     /// This is the opposite of `PyErr::fetch()`.
+    ///
+    /// On Python 3.12+ this uses `PyErr_SetRaisedException`, the counterpart of
+    /// `PyErr_GetRaisedException` used by `take`/`fetch`.
     #[inline]
+    #[cfg(not(Py_3_12))]
     pub fn restore(self) {
-        let PyErr { ptype, pvalue, ptraceback } = self;
+        let (ptype, pvalue, ptraceback) = self.into_ffi_tuple();
         unsafe {
             ffi::PyErr_Restore(ptype.steal_ptr(), pvalue.steal_ptr(), ptraceback.steal_ptr())
         }
     }
 
+    /// See the `not(Py_3_12)` overload above.
+    #[inline]
+    #[cfg(Py_3_12)]
+    pub fn restore(self) {
+        let err = self.into_normalized();
+        match err.state {
+            PyErrState::Raised(pvalue) => unsafe {
+                ffi::PyErr_SetRaisedException(pvalue.steal_ptr())
+            },
+            PyErrState::Normalized { pvalue, ptraceback, .. } => unsafe {
+                if let Some(ptraceback) = ptraceback {
+                    ffi::PyException_SetTraceback(pvalue.as_ptr(), ptraceback.as_ptr());
+                }
+                ffi::PyErr_SetRaisedException(pvalue.steal_ptr())
+            },
+            _ => unreachable!("into_normalized() always leaves self in a normalized state")
+        }
+    }
+
     /// Issue a warning message.
     /// May return a PyErr if warnings-as-errors is enabled.
     pub fn warn(py: Python<'p>, category: &PyObject, message: &str, stacklevel: i32) -> PyResult<'p, ()> {
@@ -246,6 +486,15 @@ impl <'p> PyErr<'p> {
             error_on_minusone(py, ffi::PyErr_WarnEx(category.as_ptr(), message.as_ptr(), stacklevel as ffi::Py_ssize_t))
         }
     }
+
+    /// Wraps any `std::error::Error` as a Python `RuntimeError`, using the error's
+    /// `Display` output as the message.
+    ///
+    /// This is the fallback for error types that don't get a dedicated `From` impl
+    /// (like `io::Error` does): reach for `?` when one exists, and this otherwise.
+    pub fn from_error<E: std::error::Error>(py: Python<'p>, err: E) -> PyErr<'p> {
+        PyErr::new::<exc::RuntimeError, _>(py, format!("{}", err))
+    }
 }
 
 /// Converts `PythonObjectDowncastError` to Python `TypeError`.
@@ -255,6 +504,50 @@ impl <'p> std::convert::From<PythonObjectDowncastError<'p>> for PyErr<'p> {
     }
 }
 
+/// Converts `io::Error` to the closest matching Python exception class, so that
+/// ordinary Rust I/O code can be bridged into a `PyResult` with `?`:
+/// `let f = File::open(path)?;` inside a function returning `PyResult<_>`.
+///
+/// Uses `Python::assume_gil_acquired()` because `From::from` has no way to accept a
+/// `Python` token; this is sound because the conversion only ever runs while handling
+/// a `PyResult`, i.e. with the GIL already held.
+impl <'p> std::convert::From<std::io::Error> for PyErr<'p> {
+    fn from(err: std::io::Error) -> PyErr<'p> {
+        let py = unsafe { Python::assume_gil_acquired() };
+        let exc_type = io_error_exc_type(py, &err);
+        let message = format!("{}", err);
+        let value = match err.raw_os_error() {
+            Some(errno) => (errno, message).to_py_object(py).into_object(),
+            None => (message,).to_py_object(py).into_object()
+        };
+        PyErr::new_helper(exc_type, value)
+    }
+}
+
+/// `FileNotFoundError`/`PermissionError` only exist as builtins on Python 3;
+/// on 2.7 every `io::ErrorKind` maps to the more general `OSError`.
+#[cfg(not(feature="python27-sys"))]
+fn io_error_exc_type<'p>(py: Python<'p>, err: &std::io::Error) -> PyType<'p> {
+    match err.kind() {
+        std::io::ErrorKind::NotFound => py.get_type::<exc::FileNotFoundError>(),
+        std::io::ErrorKind::PermissionDenied => py.get_type::<exc::PermissionError>(),
+        _ => py.get_type::<exc::OSError>()
+    }
+}
+
+#[cfg(feature="python27-sys")]
+fn io_error_exc_type<'p>(py: Python<'p>, _err: &std::io::Error) -> PyType<'p> {
+    py.get_type::<exc::OSError>()
+}
+
+impl <'p> std::fmt::Display for PyErr<'p> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl <'p> std::error::Error for PyErr<'p> {}
+
 /// Construct PyObject from the result of a Python FFI call that returns a new reference (owned pointer).
 /// Returns `Err(PyErr)` if the pointer is `null`.
 /// Unsafe because the pointer might be invalid.
@@ -324,6 +617,118 @@ mod tests {
         assert!(PyErr::occurred(py));
         drop(PyErr::fetch(py));
     }
-}
 
+    #[test]
+    fn take_returns_none_when_no_error_is_set() {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        assert!(PyErr::take(py).is_none());
+    }
+
+    #[test]
+    fn take_returns_some_and_clears_the_error_when_one_is_set() {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        PyErr::new_lazy_init(py.get_type::<exc::TypeError>(), None).restore();
+        let err = PyErr::take(py).expect("an error was set");
+        assert!(err.matches(&py.get_type::<exc::TypeError>().into_object()));
+        assert!(!PyErr::occurred(py));
+    }
+
+    #[test]
+    fn new_lazy_does_not_build_the_value_until_observed() {
+        use std::cell::Cell;
+        use conversion::ToPyObject;
+
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        let called = Cell::new(false);
+        let mut err = PyErr::new_lazy(py.get_type::<exc::ValueError>(), |py| {
+            called.set(true);
+            "boom".to_py_object(py).into_object()
+        });
+
+        // matches() only needs the stored type, so the factory must not run yet.
+        assert!(err.matches(&py.get_type::<exc::ValueError>().into_object()));
+        assert!(!called.get());
+
+        err.instance();
+        assert!(called.get());
+    }
 
+    #[test]
+    fn fetch_and_restore_roundtrip() {
+        // Exercises the `not(Py_3_12)` tri-tuple path and the `Py_3_12`
+        // `PyErr_GetRaisedException`/`PyErr_SetRaisedException` path alike: both
+        // must leave `fetch()` able to recover an equivalent, correctly-typed error.
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        PyErr::new::<exc::ValueError, _>(py, "boom").restore();
+        assert!(PyErr::occurred(py));
+        let err = PyErr::fetch(py);
+        assert!(err.matches(&py.get_type::<exc::ValueError>().into_object()));
+        assert!(!PyErr::occurred(py));
+    }
+
+    #[test]
+    #[cfg(not(feature="python27-sys"))]
+    fn io_error_maps_to_specific_exception_classes() {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+
+        let not_found: PyErr = std::io::Error::new(std::io::ErrorKind::NotFound, "missing").into();
+        assert!(not_found.is_instance_of::<exc::FileNotFoundError>(py));
+
+        let denied: PyErr = std::io::Error::new(std::io::ErrorKind::PermissionDenied, "nope").into();
+        assert!(denied.is_instance_of::<exc::PermissionError>(py));
+
+        let other: PyErr = std::io::Error::new(std::io::ErrorKind::Other, "weird").into();
+        assert!(other.is_instance_of::<exc::OSError>(py));
+    }
+
+    #[test]
+    #[cfg(feature="python27-sys")]
+    fn io_error_maps_to_os_error_on_python27() {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+
+        let not_found: PyErr = std::io::Error::new(std::io::ErrorKind::NotFound, "missing").into();
+        assert!(not_found.is_instance_of::<exc::OSError>(py));
+    }
+
+    #[test]
+    fn is_instance_and_is_instance_of_match_a_raised_exception() {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        let mut err = PyErr::new::<exc::ValueError, _>(py, "boom");
+
+        assert!(err.is_instance_of::<exc::ValueError>(py));
+        assert!(!err.is_instance_of::<exc::TypeError>(py));
+
+        let value_error = py.get_type::<exc::ValueError>().into_object();
+        assert!(err.is_instance(&value_error));
+        let type_error = py.get_type::<exc::TypeError>().into_object();
+        assert!(!err.is_instance(&type_error));
+    }
+
+    #[test]
+    fn is_instance_clears_the_stray_error_on_an_invalid_argument() {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        let mut err = PyErr::new::<exc::ValueError, _>(py, "boom");
+
+        // `py.None()` isn't a class or tuple of classes, so PyObject_IsInstance
+        // returns -1 with its own exception set; that must not leak out.
+        let not_a_class = py.None();
+        assert!(!err.is_instance(&not_a_class));
+        assert!(!PyErr::occurred(py));
+    }
+
+    #[test]
+    fn ptraceback_is_none_for_an_error_without_a_traceback() {
+        let gil = Python::acquire_gil();
+        let py = gil.python();
+        let mut err = PyErr::new::<exc::ValueError, _>(py, "boom");
+        assert!(err.ptraceback().is_none());
+    }
+}
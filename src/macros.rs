@@ -0,0 +1,99 @@
+// Copyright (c) 2015 Daniel Grunwald
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy of this
+// software and associated documentation files (the "Software"), to deal in the Software
+// without restriction, including without limitation the rights to use, copy, modify, merge,
+// publish, distribute, sublicense, and/or sell copies of the Software, and to permit persons
+// to whom the Software is furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all copies or
+// substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR IMPLIED,
+// INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY, FITNESS FOR A PARTICULAR
+// PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE
+// FOR ANY CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR
+// OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
+// DEALINGS IN THE SOFTWARE.
+
+/// Defines a new Python exception type that Python code can catch by name.
+///
+/// ```
+/// #[macro_use] extern crate cpython;
+///
+/// py_exception!(mymodule, CustomError);
+/// py_exception!(mymodule, CustomErrorWithBase, cpython::exc::Exception);
+/// py_exception!(mymodule, CustomErrorWithDoc, cpython::exc::Exception, "custom error docstring");
+/// # fn main() {}
+/// ```
+///
+/// This generates a `CustomError` tuple struct wrapping a `PyObject`, the same shape
+/// the built-in `objects::exc` types use, so a caught instance can be downcast back
+/// into Rust rather than just matched by type. The underlying Python class (qualified
+/// name `"mymodule.CustomError"`) is created on first use via
+/// `PyErr_NewException`/`PyErr_NewExceptionWithDoc` and cached for the remaining
+/// lifetime of the process, the same way the built-in `objects::exc` types are
+/// looked up through `PythonObjectWithTypeObject`. Use `CustomError::new(py, args)`
+/// to raise it, and `except mymodule.CustomError` to catch it from Python.
+#[macro_export]
+macro_rules! py_exception {
+    ($module: expr, $name: ident) => {
+        py_exception!($module, $name, $crate::objects::exc::Exception);
+    };
+    ($module: expr, $name: ident, $base: ty) => {
+        py_exception!(@imp $module, $name, $base, None);
+    };
+    ($module: expr, $name: ident, $base: ty, $doc: expr) => {
+        py_exception!(@imp $module, $name, $base, Some($doc));
+    };
+    (@imp $module: expr, $name: ident, $base: ty, $doc: expr) => {
+        pub struct $name<'p>($crate::objects::PyObject<'p>);
+
+        pyobject_newtype!($name);
+
+        impl <'p> $crate::python::PythonObjectWithTypeObject<'p> for $name<'p> {
+            fn type_object(py: $crate::Python<'p>) -> $crate::objects::PyType<'p> {
+                static INIT: ::std::sync::Once = ::std::sync::Once::new();
+                static mut TYPE_OBJECT: *mut $crate::_detail::ffi::PyTypeObject =
+                    0 as *mut $crate::_detail::ffi::PyTypeObject;
+
+                unsafe {
+                    INIT.call_once(|| {
+                        let base = <$base as $crate::python::PythonObjectWithTypeObject<'p>>::type_object(py);
+                        let name = concat!(stringify!($module), ".", stringify!($name), "\0");
+                        let doc: Option<&str> = $doc;
+                        let ptr = match doc {
+                            Some(doc) => {
+                                let doc = ::std::ffi::CString::new(doc).unwrap();
+                                $crate::_detail::ffi::PyErr_NewExceptionWithDoc(
+                                    name.as_ptr() as *mut _,
+                                    doc.as_ptr() as *mut _,
+                                    base.as_object().as_ptr(),
+                                    ::std::ptr::null_mut())
+                            }
+                            None => {
+                                $crate::_detail::ffi::PyErr_NewException(
+                                    name.as_ptr() as *mut _,
+                                    base.as_object().as_ptr(),
+                                    ::std::ptr::null_mut())
+                            }
+                        };
+                        assert!(!ptr.is_null());
+                        TYPE_OBJECT = ptr as *mut $crate::_detail::ffi::PyTypeObject;
+                    });
+                    $crate::objects::PyType::from_type_ptr(py, TYPE_OBJECT)
+                }
+            }
+        }
+
+        impl <'p> $name<'p> {
+            /// Creates a new `PyErr` for this exception type. Equivalent to
+            /// `PyErr::new::<Self, _>(py, args)`.
+            pub fn new<V>(py: $crate::Python<'p>, args: V) -> $crate::PyErr<'p>
+                where V: $crate::conversion::ToPyObject<'p>
+            {
+                $crate::PyErr::new::<$name, V>(py, args)
+            }
+        }
+    };
+}